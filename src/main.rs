@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use crossterm::cursor::MoveTo;
 use crossterm::style::{Color, SetForegroundColor};
 use crossterm::terminal::{Clear, ClearType};
@@ -9,7 +10,7 @@ use serde::Serialize;
 use std::fs;
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     fmt::Display,
     io::{self, stdout},
 };
@@ -27,8 +28,25 @@ trait TaskRepository {
     /// gets the vec od Uuid references
     fn ids(&self) -> Vec<Uuid>;
     fn get_all(&self) -> Vec<KeyedTask>;
-    /// remove task from repository; uses [KeyedTask] so that a task can be removed using its Uuid
-    fn remove_task(&self, t: &KeyedTask);
+    /// adds `prerequisite` to the `depends` list of `dependent`
+    fn add_dependency(&self, dependent: Uuid, prerequisite: Uuid);
+    /// marks a task as [TaskStatus::Completed] and stamps its `end` time, keeping it in the repository
+    fn complete_task(&self, id: Uuid);
+    /// appends a timestamped [Annotation] to a task
+    fn annotate_task(&self, id: Uuid, description: String);
+    /// stamps `last_used` with the current time, marking the task as recently acted on
+    fn touch(&self, id: Uuid);
+    /// union of all tags currently present across tasks in the repository
+    fn tags(&self) -> Vec<String>;
+    /// pending tasks carrying the given tag
+    fn filter_by_tag(&self, tag: &str) -> Vec<KeyedTask>;
+    /// a task is blocked while any task in its `depends` list is still [TaskStatus::Pending]
+    fn blocked(&self, id: Uuid) -> bool;
+    /// orders all tasks so that every task appears after each [TaskStatus::Pending] task it depends
+    /// on (Kahn's algorithm); dependencies on tasks that are no longer pending do not constrain the order.
+    /// if not all tasks could be ordered, returns the Uuids of every task left over: the members of a
+    /// dependency cycle and the tasks that depend on them.
+    fn topo_order(&self) -> Result<Vec<KeyedTask>, Vec<Uuid>>;
 }
 
 /// (Uuid, Task) pair
@@ -81,8 +99,111 @@ impl TaskRepository for MapTaskRepository {
             .collect::<Vec<KeyedTask>>();
         v
     }
-    fn remove_task(&self, t: &KeyedTask) {
-        self.tm.borrow_mut().remove(&t.0);
+
+    fn add_dependency(&self, dependent: Uuid, prerequisite: Uuid) {
+        if let Some(t) = self.tm.borrow_mut().get_mut(&dependent) {
+            t.depends.push(prerequisite);
+        }
+    }
+
+    fn complete_task(&self, id: Uuid) {
+        if let Some(t) = self.tm.borrow_mut().get_mut(&id) {
+            t.status = TaskStatus::Completed;
+            t.end = Some(Utc::now());
+            t.last_used = Some(Utc::now());
+        }
+    }
+
+    fn annotate_task(&self, id: Uuid, description: String) {
+        if let Some(t) = self.tm.borrow_mut().get_mut(&id) {
+            t.annotations.push(Annotation {
+                entry: Utc::now(),
+                description,
+            });
+        }
+    }
+
+    fn touch(&self, id: Uuid) {
+        if let Some(t) = self.tm.borrow_mut().get_mut(&id) {
+            t.last_used = Some(Utc::now());
+        }
+    }
+
+    fn tags(&self) -> Vec<String> {
+        let binding = self.tm.borrow();
+        let set: BTreeSet<String> = binding.values().flat_map(|t| t.tags.clone()).collect();
+        set.into_iter().collect()
+    }
+
+    fn filter_by_tag(&self, tag: &str) -> Vec<KeyedTask> {
+        self.get_all()
+            .into_iter()
+            .filter(|kt| kt.1.status == TaskStatus::Pending && kt.1.tags.iter().any(|t| t == tag))
+            .collect()
+    }
+
+    fn blocked(&self, id: Uuid) -> bool {
+        match self.get_task(id) {
+            Some(t) => t.depends.iter().any(|d| {
+                self.get_task(*d)
+                    .is_some_and(|dep| dep.status == TaskStatus::Pending)
+            }),
+            None => false,
+        }
+    }
+
+    fn topo_order(&self) -> Result<Vec<KeyedTask>, Vec<Uuid>> {
+        let all = self.get_all();
+        let pending: HashSet<Uuid> = all
+            .iter()
+            .filter(|kt| kt.1.status == TaskStatus::Pending)
+            .map(|kt| kt.0)
+            .collect();
+
+        let mut in_degree: HashMap<Uuid, usize> = HashMap::new();
+        let mut dependents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for kt in &all {
+            let deg = kt.1.depends.iter().filter(|d| pending.contains(d)).count();
+            in_degree.insert(kt.0, deg);
+            for dep in kt.1.depends.iter().filter(|d| pending.contains(d)) {
+                dependents.entry(*dep).or_default().push(kt.0);
+            }
+        }
+
+        let mut queue: VecDeque<Uuid> = in_degree
+            .iter()
+            .filter(|(_, &deg)| deg == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut emitted = Vec::new();
+        while let Some(id) = queue.pop_front() {
+            emitted.push(id);
+            if let Some(deps) = dependents.get(&id) {
+                for dep_id in deps {
+                    if let Some(deg) = in_degree.get_mut(dep_id) {
+                        *deg -= 1;
+                        if *deg == 0 {
+                            queue.push_back(*dep_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        if emitted.len() < all.len() {
+            let seen: HashSet<Uuid> = emitted.into_iter().collect();
+            return Err(all
+                .into_iter()
+                .map(|kt| kt.0)
+                .filter(|id| !seen.contains(id))
+                .collect());
+        }
+
+        let by_id: HashMap<Uuid, Task> = all.into_iter().map(|kt| (kt.0, kt.1)).collect();
+        Ok(emitted
+            .into_iter()
+            .map(|id| KeyedTask(id, by_id[&id].clone()))
+            .collect())
     }
 }
 
@@ -102,11 +223,130 @@ impl Priority {
     const VALUES: [Priority; 3] = [Priority::High, Priority::Medium, Priority::Low];
 }
 
+/// Models the Taskwarrior-compatible lifecycle of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TaskStatus {
+    #[default]
+    Pending,
+    Completed,
+    Deleted,
+}
+
+/// Value held by a user-defined attribute (UDA), modeled on Taskwarrior's UDA types.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum UdaValue {
+    Date(DateTime<Utc>),
+    Num(f64),
+    Str(String),
+}
+
+/// A timestamped free-text note attached to a task.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Annotation {
+    entry: DateTime<Utc>,
+    description: String,
+}
+
+/// How often a [TaskTemplate] should regenerate its task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Recurrence {
+    Daily,
+    Weekly,
+}
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl Recurrence {
+    const VALUES: [Recurrence; 2] = [Recurrence::Daily, Recurrence::Weekly];
+
+    fn interval(&self) -> chrono::Duration {
+        match self {
+            Recurrence::Daily => chrono::Duration::days(1),
+            Recurrence::Weekly => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+/// A recurring chore: on each [load_tasks], a fresh [Task] is instantiated from this template
+/// once its `recurrence` interval has elapsed since `last_generated`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TaskTemplate {
+    id: Uuid,
+    name: String,
+    priority: Priority,
+    #[serde(default)]
+    tags: Vec<String>,
+    recurrence: Recurrence,
+    #[serde(default)]
+    last_generated: Option<DateTime<Utc>>,
+}
+
+/// (De)serializes `Option<DateTime<Utc>>` using Taskwarrior's packed-basic timestamp format
+/// (e.g. `20231014T120000Z`) instead of chrono's default RFC3339, so `entry`/`end` round-trip
+/// with a real Taskwarrior export.
+mod tw_timestamp {
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+    pub fn serialize<S>(value: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(dt) => serializer.serialize_str(&dt.format(FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => NaiveDateTime::parse_from_str(&s, FORMAT)
+                .map(|naive| Some(DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc)))
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
 /// A task struct has name and priority.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct Task {
     name: String,
     priority: Priority,
+    /// Uuids of tasks that must be removed/completed before this one is actionable
+    #[serde(default)]
+    depends: Vec<Uuid>,
+    #[serde(default)]
+    status: TaskStatus,
+    #[serde(default, with = "tw_timestamp")]
+    entry: Option<DateTime<Utc>>,
+    #[serde(default, with = "tw_timestamp")]
+    end: Option<DateTime<Utc>>,
+    /// custom fields, flattened to top-level JSON keys so `tasks.json` stays
+    /// compatible with Taskwarrior exports carrying UDAs
+    #[serde(flatten, default)]
+    udas: BTreeMap<String, UdaValue>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    /// last time this task was selected or completed; drives
+    /// most-recently-used ordering in [select_task]
+    #[serde(default)]
+    last_used: Option<DateTime<Utc>>,
+    /// tags parsed from `#hashtag` tokens in the task's name
+    #[serde(default)]
+    tags: Vec<String>,
+    /// marks this task as generated from a [TaskTemplate], linking back to its id
+    #[serde(default)]
+    generated_from: Option<Uuid>,
 }
 impl Display for Task {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -119,8 +359,13 @@ impl Display for Task {
 enum Action {
     Quit,
     List,
+    ListAll,
     Add,
-    Remove,
+    Done,
+    AddDependency,
+    Annotate,
+    Filter,
+    AddTemplate,
     Unknown(String),
 }
 impl Display for Action {
@@ -128,15 +373,30 @@ impl Display for Action {
         let w = match self {
             Self::Quit => "Quit",
             Self::List => "List",
+            Self::ListAll => "ListAll",
             Self::Add => "Add",
-            Self::Remove => "Remove",
+            Self::Done => "Done",
+            Self::AddDependency => "AddDependency",
+            Self::Annotate => "Annotate",
+            Self::Filter => "Filter",
+            Self::AddTemplate => "AddTemplate",
             Self::Unknown(_msg) => "unknown",
         };
         write!(f, "[{:?}]", w)
     }
 }
 impl Action {
-    const VALUES: [Action; 4] = [Action::Quit, Action::List, Action::Add, Action::Remove];
+    const VALUES: [Action; 9] = [
+        Action::Quit,
+        Action::List,
+        Action::ListAll,
+        Action::Add,
+        Action::Done,
+        Action::AddDependency,
+        Action::Annotate,
+        Action::Filter,
+        Action::AddTemplate,
+    ];
 }
 
 /// Displays actions prompt and returns an action selected by user.
@@ -158,45 +418,154 @@ fn priority_to_color(p: &Priority) -> Color {
 }
 
 /// Returns String representation of the task with color-coded priority
-fn format_task(t: &Task) -> String {
+fn format_task(t: &Task, dim: bool) -> String {
+    let notes = if t.annotations.is_empty() {
+        String::new()
+    } else if t.annotations.len() == 1 {
+        " (1 note)".to_string()
+    } else {
+        format!(" ({} notes)", t.annotations.len())
+    };
+    let tags = if t.tags.is_empty() {
+        String::new()
+    } else {
+        let joined = t
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!(
+            " {}",
+            joined.with(if dim { Color::DarkGrey } else { Color::Cyan })
+        )
+    };
     format!(
-        "[{:>10}] {}\n",
+        "[{:>10}] {}{}{}\n",
         t.priority,
-        t.name.to_string().with(Color::Magenta)
+        t.name
+            .to_string()
+            .with(if dim { Color::DarkGrey } else { Color::Magenta }),
+        tags,
+        notes
     )
 }
 
+/// Splits `#hashtag` tokens out of raw task input, returning the remaining text and the tags found.
+fn parse_tags(input: &str) -> (String, Vec<String>) {
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+    for word in input.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), tags)
+}
+
 /// Prints a task to stdout
-fn print_task(t: &Task) {
-    let _ = stdout().execute(SetForegroundColor(priority_to_color(&t.priority)));
-    print!("{}", format_task(t));
+fn print_task(t: &Task, dim: bool) {
+    let color = if dim {
+        Color::DarkGrey
+    } else {
+        priority_to_color(&t.priority)
+    };
+    let _ = stdout().execute(SetForegroundColor(color));
+    print!("{}", format_task(t, dim));
 }
 
-/// Lists all tasks in repository
-fn list_tasks(tr: &dyn TaskRepository) {
-    let _ = clear();
-    let mut all = tr.get_all();
-    all.sort_by_key(|kt| kt.1.priority.clone());
-    for t in all.iter() {
-        print_task(&t.1);
+/// Prints a sorted list of tasks, dimming ones that are still blocked by a dependency.
+/// Tasks are ordered so that a task never appears before a pending task it depends on; if the
+/// repository's dependencies form a cycle, falls back to ordering by priority alone.
+fn print_task_list(tr: &dyn TaskRepository, mut tasks: Vec<KeyedTask>) {
+    match tr.topo_order() {
+        Ok(order) => {
+            let rank: HashMap<Uuid, usize> = order.iter().map(|kt| kt.0).zip(0..).collect();
+            tasks.sort_by_key(|kt| {
+                (
+                    rank.get(&kt.0).copied().unwrap_or(usize::MAX),
+                    kt.1.priority.clone(),
+                )
+            });
+        }
+        Err(_) => tasks.sort_by_key(|kt| kt.1.priority.clone()),
+    }
+    for t in tasks.iter() {
+        print_task(&t.1, tr.blocked(t.0));
     }
-    if all.is_empty() {
+    if tasks.is_empty() {
         println!("{}", "No tasks".green());
     }
 }
 
+/// Lists tasks in repository. Unless `show_completed` is set, only [TaskStatus::Pending] tasks are shown.
+fn list_tasks(tr: &dyn TaskRepository, show_completed: bool) {
+    let _ = clear();
+    let all: Vec<KeyedTask> = tr
+        .get_all()
+        .into_iter()
+        .filter(|kt| show_completed || kt.1.status == TaskStatus::Pending)
+        .collect();
+    print_task_list(tr, all);
+}
+
+/// Prompts for a name (optionally with `#tags`), a priority and a recurrence, and defines a
+/// new [TaskTemplate].
+fn add_template(templates: &mut Vec<TaskTemplate>) {
+    let raw = Text::new("Template task: ").prompt();
+    if let Ok(raw) = raw {
+        let (name, tags) = parse_tags(&raw);
+        let priority = Select::new("Priority: ", Priority::VALUES.to_vec()).prompt();
+        let recurrence = Select::new("Recurrence: ", Recurrence::VALUES.to_vec()).prompt();
+        if let (Ok(priority), Ok(recurrence)) = (priority, recurrence) {
+            templates.push(TaskTemplate {
+                id: Uuid::new_v4(),
+                name,
+                priority,
+                tags,
+                recurrence,
+                last_generated: None,
+            });
+        }
+    }
+}
+
+/// Prompts for a tag (among those present in the repository) and lists tasks carrying it.
+fn filter_by_tag(tr: &dyn TaskRepository) {
+    let tags = tr.tags();
+    if tags.is_empty() {
+        println!("No tags yet");
+        return;
+    }
+    if let Ok(tag) = Select::new("Filter by tag: ", tags).prompt() {
+        let _ = clear();
+        print_task_list(tr, tr.filter_by_tag(&tag));
+    }
+}
+
 /// Prompts for a task and its priority and adds it to repository.
 /// **NOTE***: [todo] task creation and task adding should be separated.
 fn add_task(tr: &dyn TaskRepository) {
     let t = Text::new("Task: ").prompt();
     match t {
         Ok(task) => {
+            let (name, tags) = parse_tags(&task);
             let p = Select::new("Priority: ", Priority::VALUES.to_vec()).prompt();
             match p {
                 Ok(prio) => {
                     let _ = tr.add_task(Task {
-                        name: task,
+                        name,
                         priority: prio,
+                        depends: Vec::new(),
+                        status: TaskStatus::Pending,
+                        entry: Some(Utc::now()),
+                        end: None,
+                        udas: BTreeMap::new(),
+                        annotations: Vec::new(),
+                        last_used: None,
+                        tags,
+                        generated_from: None,
                     });
                 }
                 Err(_) => println!("error reading prompt"),
@@ -208,19 +577,112 @@ fn add_task(tr: &dyn TaskRepository) {
 
 /// Formats a ListOption of KeyedTask (to be used in [[select_task]].
 fn task_selection_formatter(lo: ListOption<&KeyedTask>) -> String {
-    format_task(&lo.value.1)
+    format_task(&lo.value.1, false)
+}
+
+/// Keeps, for each distinct name+priority, only the most-recently-used entry. All `tasks` are
+/// expected to share the same [TaskStatus] already (see [select_task]), so this can't shadow a
+/// completed task behind a pending one or vice versa.
+fn dedup_by_name_and_priority(tasks: Vec<KeyedTask>) -> Vec<KeyedTask> {
+    let mut best: BTreeMap<(String, Priority), KeyedTask> = BTreeMap::new();
+    for kt in tasks {
+        let key = (kt.1.name.clone(), kt.1.priority.clone());
+        let keep = match best.get(&key) {
+            Some(existing) => existing.1.last_used < kt.1.last_used,
+            None => true,
+        };
+        if keep {
+            best.insert(key, kt);
+        }
+    }
+    best.into_values().collect()
 }
 
-/// Promppts for a task.
+/// Promppts for a task among the [TaskStatus::Pending] ones. Recently-used tasks (see
+/// [[Task::last_used]]) are listed first, most recent first, the remainder sorted by priority
+/// as before; entries sharing a name and priority are deduplicated, keeping the
+/// most-recently-used one. When the list mixes both groups, a `recently used` line is printed
+/// above the prompt; the list itself holds no separator between the two groups.
 fn select_task(tr: &dyn TaskRepository) -> Option<KeyedTask> {
-    let task_repr: Vec<KeyedTask> = tr.get_all();
+    let pending: Vec<KeyedTask> = tr
+        .get_all()
+        .into_iter()
+        .filter(|kt| kt.1.status == TaskStatus::Pending)
+        .collect();
+    let mut task_repr: Vec<KeyedTask> = dedup_by_name_and_priority(pending);
+    task_repr.sort_by(|a, b| match (a.1.last_used, b.1.last_used) {
+        (Some(al), Some(bl)) => bl.cmp(&al),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.1.priority.cmp(&b.1.priority),
+    });
+    let recent_count = task_repr
+        .iter()
+        .filter(|kt| kt.1.last_used.is_some())
+        .count();
+    if recent_count > 0 && recent_count < task_repr.len() {
+        println!("{}", "----- recently used -----".dark_grey());
+    }
     let selected = Select::new("Select one of tasks: ", task_repr)
         .with_formatter(&task_selection_formatter)
         .prompt();
 
+    if let Ok(ref kt) = selected {
+        tr.touch(kt.0);
+    }
     selected.ok()
 }
 
+/// True if adding a `dependent` -> `prerequisite` edge would close a cycle,
+/// i.e. `prerequisite` already (transitively) depends on `dependent`.
+fn creates_cycle(tr: &dyn TaskRepository, dependent: Uuid, prerequisite: Uuid) -> bool {
+    if dependent == prerequisite {
+        return true;
+    }
+    let mut stack = vec![prerequisite];
+    let mut visited = HashSet::new();
+    while let Some(id) = stack.pop() {
+        if id == dependent {
+            return true;
+        }
+        if !visited.insert(id) {
+            continue;
+        }
+        if let Some(t) = tr.get_task(id) {
+            stack.extend(t.depends);
+        }
+    }
+    false
+}
+
+/// Prompts for a dependent task and a prerequisite task, then links them unless that would
+/// create a dependency cycle.
+fn add_dependency(tr: &dyn TaskRepository) {
+    println!("Select the task that should wait:");
+    let dependent = select_task(tr);
+    println!("Select the task it depends on:");
+    let prerequisite = select_task(tr);
+    match (dependent, prerequisite) {
+        (Some(d), Some(p)) => {
+            if creates_cycle(tr, d.0, p.0) {
+                println!("{}", "Cannot add dependency: would create a cycle".red());
+            } else {
+                tr.add_dependency(d.0, p.0);
+            }
+        }
+        _ => println!("Dependency not added"),
+    }
+}
+
+/// Prompts for a task and appends a timestamped annotation to it.
+fn annotate(tr: &dyn TaskRepository) {
+    if let Some(t) = select_task(tr) {
+        if let Ok(note) = Text::new("Annotation: ").prompt() {
+            tr.annotate_task(t.0, note);
+        }
+    }
+}
+
 /// Keeps state between loop executions (currently, defers the removal action of a selected task to next itetation of the loop. Can be useful also to edit a selected task (not implemented yet).
 #[derive(Default)]
 struct State {
@@ -230,15 +692,20 @@ struct State {
 }
 
 /// Executes provided action (unless state contains deferred action which has higher  priority).
-fn execute_action(a: Action, tr: &dyn TaskRepository, state: State) -> State {
+fn execute_action(
+    a: Action,
+    tr: &dyn TaskRepository,
+    templates: &mut Vec<TaskTemplate>,
+    state: State,
+) -> State {
     let mut should_continue = state.should_continue;
     let mut action_opt = state.action;
     let mut task_opt = state.task;
     if let Some(ref action) = action_opt {
         if let Some(ref t) = task_opt {
             match action {
-                Action::Remove => {
-                    tr.remove_task(&t);
+                Action::Done => {
+                    tr.complete_task(t.0);
                     action_opt = None;
                     task_opt = None;
                 }
@@ -251,12 +718,17 @@ fn execute_action(a: Action, tr: &dyn TaskRepository, state: State) -> State {
             let _ = clear();
             should_continue = false
         }
-        Action::List => list_tasks(tr),
+        Action::List => list_tasks(tr, false),
+        Action::ListAll => list_tasks(tr, true),
         Action::Add => add_task(tr),
-        Action::Remove => {
+        Action::Done => {
             action_opt = Some(a);
             task_opt = select_task(tr);
         }
+        Action::AddDependency => add_dependency(tr),
+        Action::Annotate => annotate(tr),
+        Action::Filter => filter_by_tag(tr),
+        Action::AddTemplate => add_template(templates),
         Action::Unknown(s) => {
             println!("Action undefined: {}", s);
         }
@@ -270,22 +742,41 @@ fn execute_action(a: Action, tr: &dyn TaskRepository, state: State) -> State {
 /// Default path to read from and store tasks
 const PATH: &str = "tasks.json";
 
-/// loads tasks to repository from [[PATH]]
-fn load_tasks(tr: &dyn TaskRepository) -> io::Result<()> {
+/// loads tasks to repository from [[PATH]], then regenerates any [TaskTemplate] that is due
+fn load_tasks(tr: &dyn TaskRepository, templates: &mut [TaskTemplate]) -> io::Result<()> {
     if let Ok(contents) = fs::read_to_string(PATH) {
         let tasks: Vec<Task> = serde_json::from_str(&contents)?;
         for t in tasks {
             tr.add_task(t.clone());
         }
     };
+    generate_due_tasks(tr, templates);
     if tr.get_all().is_empty() {
         let t = Task {
             name: "Learn Rust".to_string(),
             priority: Priority::High,
+            depends: Vec::new(),
+            status: TaskStatus::Pending,
+            entry: Some(Utc::now()),
+            end: None,
+            udas: BTreeMap::new(),
+            annotations: Vec::new(),
+            last_used: None,
+            tags: Vec::new(),
+            generated_from: None,
         };
         let o = Task {
             name: "Learn NeoVim".to_string(),
             priority: Priority::Medium,
+            depends: Vec::new(),
+            status: TaskStatus::Pending,
+            entry: Some(Utc::now()),
+            end: None,
+            udas: BTreeMap::new(),
+            annotations: Vec::new(),
+            last_used: None,
+            tags: Vec::new(),
+            generated_from: None,
         };
         tr.add_task(t);
         tr.add_task(o);
@@ -299,6 +790,50 @@ fn save_tasks(tr: &dyn TaskRepository) -> io::Result<()> {
     fs::write(PATH, serde_json::to_string(&v)?)
 }
 
+/// Default path to read from and store task templates
+const TEMPLATES_PATH: &str = "templates.json";
+
+/// loads templates from [[TEMPLATES_PATH]]; an absent or unreadable file yields no templates
+fn load_templates() -> Vec<TaskTemplate> {
+    fs::read_to_string(TEMPLATES_PATH)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Saves templates to a file denoted by [[TEMPLATES_PATH]]
+fn save_templates(templates: &[TaskTemplate]) -> io::Result<()> {
+    fs::write(TEMPLATES_PATH, serde_json::to_string(templates)?)
+}
+
+/// Instantiates a fresh [Task] for every template whose recurrence interval has elapsed since
+/// it was last generated, and stamps `last_generated` on the template.
+fn generate_due_tasks(tr: &dyn TaskRepository, templates: &mut [TaskTemplate]) {
+    let now = Utc::now();
+    for tmpl in templates.iter_mut() {
+        let due = match tmpl.last_generated {
+            Some(last) => now - last >= tmpl.recurrence.interval(),
+            None => true,
+        };
+        if due {
+            tr.add_task(Task {
+                name: tmpl.name.clone(),
+                priority: tmpl.priority.clone(),
+                depends: Vec::new(),
+                status: TaskStatus::Pending,
+                entry: Some(now),
+                end: None,
+                udas: BTreeMap::new(),
+                annotations: Vec::new(),
+                last_used: None,
+                tags: tmpl.tags.clone(),
+                generated_from: Some(tmpl.id),
+            });
+            tmpl.last_generated = Some(now);
+        }
+    }
+}
+
 /// Clears stdout
 fn clear() -> io::Result<()> {
     stdout()
@@ -309,7 +844,8 @@ fn clear() -> io::Result<()> {
 
 fn main() -> io::Result<()> {
     let tr = MapTaskRepository::new();
-    load_tasks(&tr)?;
+    let mut templates = load_templates();
+    load_tasks(&tr, &mut templates)?;
     let mut curr_state = State {
         should_continue: true,
         task: None,
@@ -319,8 +855,255 @@ fn main() -> io::Result<()> {
     clear()?;
     while curr_state.should_continue {
         let a = display_actions();
-        curr_state = execute_action(a, &tr, curr_state);
+        curr_state = execute_action(a, &tr, &mut templates, curr_state);
     }
     save_tasks(&tr)?;
+    save_templates(&templates)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk_task(name: &str, priority: Priority) -> Task {
+        Task {
+            name: name.to_string(),
+            priority,
+            depends: Vec::new(),
+            status: TaskStatus::Pending,
+            entry: Some(Utc::now()),
+            end: None,
+            udas: BTreeMap::new(),
+            annotations: Vec::new(),
+            last_used: None,
+            tags: Vec::new(),
+            generated_from: None,
+        }
+    }
+
+    #[test]
+    fn topo_order_places_prerequisites_before_dependents() {
+        let tr = MapTaskRepository::new();
+        let a = tr.add_task(mk_task("a", Priority::Low));
+        let b = tr.add_task(mk_task("b", Priority::Low));
+        tr.add_dependency(b, a);
+
+        let order = tr.topo_order().expect("no cycle");
+        let pos = |id: Uuid| order.iter().position(|kt| kt.0 == id).unwrap();
+        assert!(pos(a) < pos(b));
+    }
+
+    #[test]
+    fn topo_order_reports_cycle_members() {
+        let tr = MapTaskRepository::new();
+        let a = tr.add_task(mk_task("a", Priority::Low));
+        let b = tr.add_task(mk_task("b", Priority::Low));
+        tr.add_dependency(b, a);
+        tr.add_dependency(a, b);
+
+        let cycle = tr.topo_order().expect_err("a <-> b is a cycle");
+        assert_eq!(cycle.len(), 2);
+        assert!(cycle.contains(&a));
+        assert!(cycle.contains(&b));
+    }
+
+    #[test]
+    fn creates_cycle_detects_transitive_dependency() {
+        let tr = MapTaskRepository::new();
+        let a = tr.add_task(mk_task("a", Priority::Low));
+        let b = tr.add_task(mk_task("b", Priority::Low));
+        tr.add_dependency(b, a);
+
+        assert!(creates_cycle(&tr, a, b));
+        assert!(!creates_cycle(&tr, b, a));
+    }
+
+    #[test]
+    fn task_entry_end_round_trip_taskwarrior_format() {
+        let mut t = mk_task("a", Priority::Low);
+        t.entry = Some(Utc::now());
+        t.end = Some(Utc::now());
+
+        let json = serde_json::to_string(&t).unwrap();
+        assert!(
+            json.contains("\"entry\":\"20"),
+            "entry should be packed-basic: {json}"
+        );
+        let back: Task = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            t.entry.unwrap().format("%Y%m%dT%H%M%SZ").to_string(),
+            back.entry.unwrap().format("%Y%m%dT%H%M%SZ").to_string()
+        );
+        assert_eq!(
+            t.end.unwrap().format("%Y%m%dT%H%M%SZ").to_string(),
+            back.end.unwrap().format("%Y%m%dT%H%M%SZ").to_string()
+        );
+    }
+
+    fn mk_template(recurrence: Recurrence, last_generated: Option<DateTime<Utc>>) -> TaskTemplate {
+        TaskTemplate {
+            id: Uuid::new_v4(),
+            name: "water plants".to_string(),
+            priority: Priority::Low,
+            tags: Vec::new(),
+            recurrence,
+            last_generated,
+        }
+    }
+
+    #[test]
+    fn generate_due_tasks_generates_once_interval_has_elapsed() {
+        let tr = MapTaskRepository::new();
+        let mut templates = vec![mk_template(
+            Recurrence::Daily,
+            Some(Utc::now() - chrono::Duration::days(2)),
+        )];
+
+        generate_due_tasks(&tr, &mut templates);
+
+        let generated = tr.get_all();
+        assert_eq!(generated.len(), 1);
+        assert_eq!(generated[0].1.generated_from, Some(templates[0].id));
+        assert!(templates[0].last_generated.is_some());
+    }
+
+    #[test]
+    fn generate_due_tasks_skips_template_not_yet_due() {
+        let tr = MapTaskRepository::new();
+        let last_generated = Some(Utc::now() - chrono::Duration::hours(1));
+        let mut templates = vec![mk_template(Recurrence::Daily, last_generated)];
+
+        generate_due_tasks(&tr, &mut templates);
+
+        assert!(tr.get_all().is_empty());
+        assert_eq!(templates[0].last_generated, last_generated);
+    }
+
+    #[test]
+    fn generate_due_tasks_does_not_double_generate_on_immediate_recheck() {
+        let tr = MapTaskRepository::new();
+        let mut templates = vec![mk_template(Recurrence::Daily, None)];
+
+        generate_due_tasks(&tr, &mut templates);
+        generate_due_tasks(&tr, &mut templates);
+
+        assert_eq!(tr.get_all().len(), 1);
+    }
+
+    #[test]
+    fn parse_tags_splits_hashtags_from_the_remaining_text() {
+        let (name, tags) = parse_tags("buy milk #shopping #errand");
+        assert_eq!(name, "buy milk");
+        assert_eq!(tags, vec!["shopping".to_string(), "errand".to_string()]);
+    }
+
+    #[test]
+    fn tags_returns_the_union_of_tags_across_tasks() {
+        let tr = MapTaskRepository::new();
+        let mut a = mk_task("a", Priority::Low);
+        a.tags = vec!["home".to_string()];
+        let mut b = mk_task("b", Priority::Low);
+        b.tags = vec!["work".to_string(), "home".to_string()];
+        tr.add_task(a);
+        tr.add_task(b);
+
+        assert_eq!(tr.tags(), vec!["home".to_string(), "work".to_string()]);
+    }
+
+    #[test]
+    fn filter_by_tag_excludes_non_pending_tasks() {
+        let tr = MapTaskRepository::new();
+        let mut pending = mk_task("a", Priority::Low);
+        pending.tags = vec!["home".to_string()];
+        let pending_id = tr.add_task(pending);
+        let mut done = mk_task("b", Priority::Low);
+        done.tags = vec!["home".to_string()];
+        let done_id = tr.add_task(done);
+        tr.complete_task(done_id);
+
+        let matches = tr.filter_by_tag("home");
+        let ids: Vec<Uuid> = matches.iter().map(|kt| kt.0).collect();
+        assert_eq!(ids, vec![pending_id]);
+        assert!(!ids.contains(&done_id));
+    }
+
+    #[test]
+    fn uda_round_trips_as_flattened_top_level_json_keys() {
+        let mut t = mk_task("a", Priority::Low);
+        t.udas.insert("estimate".to_string(), UdaValue::Num(3.0));
+        t.udas
+            .insert("project".to_string(), UdaValue::Str("crate".to_string()));
+
+        let json = serde_json::to_string(&t).unwrap();
+        assert!(
+            json.contains("\"estimate\":3.0") || json.contains("\"estimate\":3"),
+            "udas should flatten to top-level keys: {json}"
+        );
+        assert!(
+            !json.contains("\"udas\""),
+            "udas key itself should not appear: {json}"
+        );
+
+        let back: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            back.udas.get("project"),
+            Some(&UdaValue::Str("crate".to_string()))
+        );
+        match back.udas.get("estimate") {
+            Some(UdaValue::Num(n)) => assert_eq!(*n, 3.0),
+            other => panic!("expected UdaValue::Num, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn annotate_task_appends_timestamped_annotation() {
+        let tr = MapTaskRepository::new();
+        let a = tr.add_task(mk_task("a", Priority::Low));
+
+        tr.annotate_task(a, "first note".to_string());
+        tr.annotate_task(a, "second note".to_string());
+
+        let t = tr.get_task(a).unwrap();
+        assert_eq!(t.annotations.len(), 2);
+        assert_eq!(t.annotations[0].description, "first note");
+        assert_eq!(t.annotations[1].description, "second note");
+    }
+
+    #[test]
+    fn blocked_true_while_dependency_is_pending_false_once_completed() {
+        let tr = MapTaskRepository::new();
+        let a = tr.add_task(mk_task("a", Priority::Low));
+        let b = tr.add_task(mk_task("b", Priority::Low));
+        tr.add_dependency(b, a);
+
+        assert!(tr.blocked(b));
+        tr.complete_task(a);
+        assert!(!tr.blocked(b));
+    }
+
+    #[test]
+    fn dedup_keeps_most_recently_used_of_matching_name_and_priority() {
+        let older = KeyedTask(Uuid::new_v4(), {
+            let mut t = mk_task("same", Priority::Low);
+            t.last_used = Some(Utc::now() - chrono::Duration::hours(1));
+            t
+        });
+        let newer = KeyedTask(Uuid::new_v4(), {
+            let mut t = mk_task("same", Priority::Low);
+            t.last_used = Some(Utc::now());
+            t
+        });
+        let newer_id = newer.0;
+        let distinct = KeyedTask(Uuid::new_v4(), mk_task("other", Priority::High));
+        let distinct_id = distinct.0;
+
+        let deduped = dedup_by_name_and_priority(vec![older, newer, distinct]);
+
+        assert_eq!(deduped.len(), 2);
+        let ids: Vec<Uuid> = deduped.iter().map(|kt| kt.0).collect();
+        assert!(ids.contains(&newer_id));
+        assert!(ids.contains(&distinct_id));
+    }
+}